@@ -0,0 +1,30 @@
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// How a Mattermost session token is obtained
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthProvider {
+    /// Username + password login via `/users/login` (current behavior)
+    Password,
+    /// A pre-issued personal access token; skips `/users/login` entirely
+    PersonalAccessToken,
+    /// LDAP-style bind: credentials are validated against an LDAP server
+    /// before a Mattermost token is requested
+    Ldap,
+}
+
+impl Default for AuthProvider {
+    fn default() -> Self {
+        AuthProvider::Password
+    }
+}
+
+/// Validate `bind_dn`/`password` against an LDAP server by performing a simple bind
+pub fn ldap_bind(ldap_url: &str, bind_dn: &str, password: &str) -> Result<(), Error> {
+    let mut ldap = ldap3::LdapConn::new(ldap_url).map_err(|err| Error::Backend(err.to_string()))?;
+    ldap.simple_bind(bind_dn, password)
+        .and_then(|result| result.success())
+        .map_err(|err| Error::Backend(err.to_string()))?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use super::MessengerBackend;
+use crate::{Error, Settings};
+use async_trait::async_trait;
+use matrix_sdk::{
+    ruma::{
+        api::client::r0::room::create_room::Request as CreateRoomRequest,
+        events::room::message::MessageEventContent,
+        RoomId, UserId,
+    },
+    Client,
+};
+use std::convert::TryFrom;
+
+/// Matrix backend; sends encrypted payloads as messages into a room resolved
+/// from the recipient's Matrix user id (e.g. `@alice:example.org`)
+pub struct Matrix {
+    settings: Settings,
+    client: Option<Client>,
+}
+
+impl Matrix {
+    pub fn new(settings: Settings) -> Self {
+        Matrix {
+            settings,
+            client: None,
+        }
+    }
+
+    fn client(&self) -> Result<&Client, Error> {
+        self.client.as_ref().ok_or(Error::TokenMissing)
+    }
+}
+
+#[async_trait]
+impl MessengerBackend for Matrix {
+    async fn authenticate(&mut self) -> Result<(), Error> {
+        let homeserver = url::Url::parse(&self.settings.api_url)
+            .map_err(|err| Error::Backend(err.to_string()))?;
+        let client = Client::new(homeserver).map_err(|err| Error::Backend(err.to_string()))?;
+        client
+            .login(
+                &self.settings.username,
+                &self.settings.password,
+                None,
+                Some("mattercrypt"),
+            )
+            .await
+            .map_err(|err| Error::Backend(err.to_string()))?;
+        self.client = Some(client);
+        Ok(())
+    }
+
+    async fn resolve_recipient(&self, recipient: &str) -> Result<String, Error> {
+        // Matrix user ids double as the recipient identifier we hand back.
+        UserId::try_from(recipient).map_err(|err| Error::Backend(err.to_string()))?;
+        Ok(recipient.to_string())
+    }
+
+    async fn open_direct_channel(&self, recipient_id: &str) -> Result<String, Error> {
+        let client = self.client()?;
+        let user_id = UserId::try_from(recipient_id).map_err(|err| Error::Backend(err.to_string()))?;
+        let invite = [user_id];
+
+        let mut request = CreateRoomRequest::new();
+        request.is_direct = true;
+        request.invite = &invite;
+
+        let response = client
+            .create_room(request)
+            .await
+            .map_err(|err| Error::Backend(err.to_string()))?;
+        Ok(response.room_id.to_string())
+    }
+
+    async fn send_post(
+        &self,
+        channel_id: &str,
+        message: &str,
+        file_ids: &[String],
+    ) -> Result<(), Error> {
+        if !file_ids.is_empty() {
+            return Err(Error::Backend(
+                "file attachments are not yet supported on the Matrix backend".to_string(),
+            ));
+        }
+
+        let client = self.client()?;
+        let room_id =
+            RoomId::try_from(channel_id).map_err(|err| Error::Backend(err.to_string()))?;
+        let room = client
+            .get_joined_room(&room_id)
+            .ok_or_else(|| Error::Backend(format!("not joined to room {}", channel_id)))?;
+        room.send(MessageEventContent::text_plain(message), None)
+            .await
+            .map_err(|err| Error::Backend(err.to_string()))?;
+        Ok(())
+    }
+
+    async fn upload_file(&self, _channel_id: &str, _filename: &str, _data: &[u8]) -> Result<String, Error> {
+        Err(Error::Backend(
+            "file attachments are not yet supported on the Matrix backend".to_string(),
+        ))
+    }
+
+    fn own_identifier(&self) -> &str {
+        &self.settings.username
+    }
+}
@@ -0,0 +1,238 @@
+use super::MessengerBackend;
+use crate::auth::{self, AuthProvider};
+use crate::{Error, Settings};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Escape a filename for use inside a multipart `Content-Disposition` header:
+/// strip CR/LF (which would corrupt the header framing) and backslash-escape
+/// quotes so the filename can't break out of the quoted-string.
+fn sanitize_multipart_filename(filename: &str) -> String {
+    filename
+        .chars()
+        .filter(|c| *c != '\r' && *c != '\n')
+        .collect::<String>()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+}
+
+#[derive(Debug)]
+struct Token(String);
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: String,
+    email: String,
+    #[allow(dead_code)]
+    first_name: String,
+    #[allow(dead_code)]
+    last_name: String,
+    #[allow(dead_code)]
+    nickname: String,
+}
+
+/// Mattermost REST API backend (`/users/login`, `/channels/direct`, `/posts`)
+pub struct Mattermost {
+    settings: Settings,
+    token: Option<Token>,
+    own_user: Option<User>,
+}
+
+impl Mattermost {
+    pub fn new(settings: Settings) -> Self {
+        Mattermost {
+            settings,
+            token: None,
+            own_user: None,
+        }
+    }
+
+    fn token(&self) -> Result<&Token, Error> {
+        self.token.as_ref().ok_or(Error::TokenMissing)
+    }
+
+    fn own_user(&self) -> Result<&User, Error> {
+        self.own_user.as_ref().ok_or(Error::TokenMissing)
+    }
+
+    /// Username + password login via `/users/login`, used as-is for the
+    /// `Password` provider and after a successful bind for the `Ldap` provider.
+    async fn login_with_password(&mut self) -> Result<(), Error> {
+        let data = serde_json::json!({
+            "login_id": self.settings.username,
+            "password": self.settings.password,
+        });
+
+        let uri = format!("{}/users/login", self.settings.api_url);
+        let mut response = surf::post(uri).body_json(&data)?.await?;
+        let token = response.header("Token").ok_or(Error::TokenMissing)?;
+        self.token = Some(Token(format!("Bearer {}", token)));
+        self.own_user = Some(response.body_json::<User>().await?);
+        Ok(())
+    }
+
+    /// Fetch the authenticated user's own details, used by the
+    /// `PersonalAccessToken` provider which skips `/users/login` entirely.
+    async fn fetch_own_user(&self) -> Result<User, Error> {
+        let token = self.token()?;
+        let uri = format!("{}/users/me", self.settings.api_url);
+        let user = surf::get(uri)
+            .set_header("Authorization", token.0.clone())
+            .recv_json()
+            .await?;
+        Ok(user)
+    }
+
+    /// List the direct message channels the authenticated user is a member of.
+    /// Used by `--read` mode, which is Mattermost-only for now.
+    pub async fn list_direct_channels(&self) -> Result<Vec<String>, Error> {
+        let token = self.token()?;
+        let uri = format!(
+            "{}/users/{}/channels",
+            self.settings.api_url,
+            self.own_user()?.id
+        );
+        let channels: Vec<Value> = surf::get(uri)
+            .set_header("Authorization", token.0.clone())
+            .recv_json()
+            .await?;
+
+        Ok(channels
+            .into_iter()
+            .filter(|channel| channel["type"] == "D")
+            .filter_map(|channel| channel["id"].as_str().map(|id| id.to_string()))
+            .collect())
+    }
+
+    /// Fetch the most recent posts of a channel, in chronological order.
+    pub async fn fetch_posts(&self, channel_id: &str) -> Result<Vec<String>, Error> {
+        let token = self.token()?;
+        let uri = format!("{}/channels/{}/posts", self.settings.api_url, channel_id);
+        let response: Value = surf::get(uri)
+            .set_header("Authorization", token.0.clone())
+            .recv_json()
+            .await?;
+
+        let order = response["order"].as_array().cloned().unwrap_or_default();
+        Ok(order
+            .iter()
+            .rev()
+            .filter_map(|id| response["posts"][id.as_str()?]["message"].as_str())
+            .map(|message| message.to_string())
+            .collect())
+    }
+}
+
+#[async_trait]
+impl MessengerBackend for Mattermost {
+    async fn authenticate(&mut self) -> Result<(), Error> {
+        match self.settings.auth_provider {
+            AuthProvider::Password => self.login_with_password().await,
+            AuthProvider::PersonalAccessToken => {
+                self.token = Some(Token(format!("Bearer {}", self.settings.password)));
+                self.own_user = Some(self.fetch_own_user().await?);
+                Ok(())
+            }
+            AuthProvider::Ldap => {
+                let ldap_url = self
+                    .settings
+                    .ldap_url
+                    .as_deref()
+                    .ok_or_else(|| Error::Backend("missing ldap_url setting".to_string()))?;
+                let bind_dn = self
+                    .settings
+                    .ldap_bind_dn
+                    .as_deref()
+                    .unwrap_or(&self.settings.username);
+                auth::ldap_bind(ldap_url, bind_dn, &self.settings.password)?;
+                self.login_with_password().await
+            }
+        }
+    }
+
+    async fn resolve_recipient(&self, recipient: &str) -> Result<String, Error> {
+        let token = self.token()?;
+        let uri = format!("{}/users/email/{}", self.settings.api_url, recipient);
+        let user: User = surf::get(uri)
+            .set_header("Authorization", token.0.clone())
+            .recv_json()
+            .await?;
+        Ok(user.id)
+    }
+
+    async fn open_direct_channel(&self, recipient_id: &str) -> Result<String, Error> {
+        let token = self.token()?;
+        let own_id = &self.own_user()?.id;
+        let data = serde_json::json!(&[own_id, recipient_id]);
+        let uri = format!("{}/channels/direct", self.settings.api_url);
+        let response = surf::post(uri)
+            .set_header("Authorization", token.0.clone())
+            .body_json(&data)?
+            .recv_string()
+            .await?;
+        let v: Value = serde_json::from_str(&response)?;
+        Ok(v["id"].as_str().unwrap().to_string())
+    }
+
+    async fn send_post(&self, channel_id: &str, message: &str, file_ids: &[String]) -> Result<(), Error> {
+        let token = self.token()?;
+        let data = serde_json::json!({
+            "channel_id": channel_id,
+            "file_ids": file_ids,
+            "message": message,
+        });
+
+        let uri = format!("{}/posts", self.settings.api_url);
+        surf::post(uri)
+            .set_header("Authorization", token.0.clone())
+            .body_json(&data)?
+            .recv_string()
+            .await?;
+        Ok(())
+    }
+
+    async fn upload_file(&self, channel_id: &str, filename: &str, data: &[u8]) -> Result<String, Error> {
+        let token = self.token()?;
+        let boundary = "mattercryptfileboundary";
+        let mut body = Vec::new();
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(b"Content-Disposition: form-data; name=\"channel_id\"\r\n\r\n");
+        body.extend_from_slice(channel_id.as_bytes());
+        body.extend_from_slice(b"\r\n");
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(
+            format!(
+                "Content-Disposition: form-data; name=\"files\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                sanitize_multipart_filename(filename)
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        let uri = format!("{}/files", self.settings.api_url);
+        let response: Value = surf::post(uri)
+            .set_header("Authorization", token.0.clone())
+            .set_header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body_bytes(body)
+            .recv_json()
+            .await?;
+
+        response["file_infos"][0]["id"]
+            .as_str()
+            .map(|id| id.to_string())
+            .ok_or_else(|| Error::Backend("file upload did not return a file id".to_string()))
+    }
+
+    fn own_identifier(&self) -> &str {
+        self.own_user
+            .as_ref()
+            .map(|user| user.email.as_str())
+            .unwrap_or(&self.settings.username)
+    }
+}
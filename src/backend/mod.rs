@@ -0,0 +1,39 @@
+mod mattermost;
+mod matrix;
+
+pub use mattermost::Mattermost;
+pub use matrix::Matrix;
+
+use crate::Error;
+use async_trait::async_trait;
+
+/// Transport-agnostic messenger used to deliver GPG-encrypted payloads.
+///
+/// Implementors are responsible for authenticating, mapping a recipient the
+/// user typed on the command line to whatever identifier the backend uses,
+/// and posting the already-encrypted message into a direct channel/room.
+#[async_trait]
+pub trait MessengerBackend {
+    /// Authenticate against the backend, caching whatever session state later
+    /// calls need.
+    async fn authenticate(&mut self) -> Result<(), Error>;
+
+    /// Resolve a human-entered recipient (email, username, Matrix id, ...) to
+    /// a backend-specific user id.
+    async fn resolve_recipient(&self, recipient: &str) -> Result<String, Error>;
+
+    /// Open (or reuse) a direct channel/room with the given user id.
+    async fn open_direct_channel(&self, recipient_id: &str) -> Result<String, Error>;
+
+    /// Post a message into the given channel/room, optionally attaching
+    /// previously uploaded files by id.
+    async fn send_post(&self, channel_id: &str, message: &str, file_ids: &[String]) -> Result<(), Error>;
+
+    /// Upload a file to the backend, returning an id that `send_post` can
+    /// attach to a message.
+    async fn upload_file(&self, channel_id: &str, filename: &str, data: &[u8]) -> Result<String, Error>;
+
+    /// Identifier (e.g. email) of the authenticated user, used to resolve our
+    /// own key when `--encrypt-for-self` is set.
+    fn own_identifier(&self) -> &str;
+}
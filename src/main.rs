@@ -1,11 +1,20 @@
+mod auth;
+mod backend;
+mod secrets;
+
 use async_std::task;
+use auth::AuthProvider;
+use backend::{Mattermost, Matrix, MessengerBackend};
 use colored::*;
-use dialoguer::{theme::ColorfulTheme, Input, PasswordInput};
+use dialoguer::{theme::ColorfulTheme, Confirmation, Input, PasswordInput, Select};
 use dirs::config_dir;
-use gpgme::{Context, Protocol};
+use gpgme::{Context, Key, Protocol};
 use keyring;
+use secrets::{EncryptedSecret, SecretStorage};
 use serde::{Deserialize, Serialize};
-use serde_json::{self, Value};
+use serde_json;
+use sha1::Sha1;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, ErrorKind, Read, Write};
 use std::path::PathBuf;
@@ -15,6 +24,7 @@ use thiserror::Error as ThisError;
 
 const KEYRING_SERVICE: &str = "mattercryptclient";
 const SETTINGS_FILE_NAME: &str = "mcc";
+const DEFAULT_KEYSERVER_URL: &str = "https://keys.openpgp.org";
 
 #[derive(ThisError, Debug)]
 enum Error {
@@ -32,6 +42,12 @@ enum Error {
     KeyUtf8(#[from] Option<std::str::Utf8Error>),
     #[error("Keyring error {}", .0)]
     Keyring(#[from] keyring::KeyringError),
+    #[error("No key found for recipient {}", .0)]
+    KeyNotFound(String),
+    #[error("Messenger backend error: {}", .0)]
+    Backend(String),
+    #[error("Failed to encrypt/decrypt stored secret")]
+    Crypto,
 }
 
 #[derive(StructOpt, Debug)]
@@ -41,140 +57,391 @@ struct Opt {
     to: Vec<String>,
     #[structopt(short, long)]
     sign: bool,
+    #[structopt(long)]
+    encrypt_for_self: bool,
     #[structopt(short, long, parse(from_os_str))]
     file: Option<PathBuf>,
     #[structopt(long)]
     reinit: bool,
+    #[structopt(long)]
+    read: bool,
     #[structopt()]
     message: Option<String>,
 }
 
-#[derive(Debug)]
-struct Token(String);
-
-#[derive(Debug)]
-struct ChannelId(String);
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum BackendKind {
+    Mattermost,
+    Matrix,
+}
 
-#[derive(Debug, Deserialize)]
-struct User {
-    /// User id
-    id: String,
-    email: String,
-    first_name: String,
-    last_name: String,
-    nickname: String,
+impl Default for BackendKind {
+    fn default() -> Self {
+        BackendKind::Mattermost
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Settings {
     api_url: String,
     username: String,
     password: String,
+    auto_sign: bool,
+    encrypt_for_self: bool,
+    keyserver_url: String,
+    auto_decrypt: bool,
+    verify_signatures: bool,
+    backend: BackendKind,
+    secret_storage: SecretStorage,
+    auth_provider: AuthProvider,
+    ldap_url: Option<String>,
+    ldap_bind_dn: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct StoredSettings {
     api_url: String,
     username: String,
+    #[serde(default)]
+    auto_sign: bool,
+    #[serde(default)]
+    encrypt_for_self: bool,
+    #[serde(default = "default_keyserver_url")]
+    keyserver_url: String,
+    #[serde(default = "default_true")]
+    auto_decrypt: bool,
+    #[serde(default = "default_true")]
+    verify_signatures: bool,
+    #[serde(default)]
+    backend: BackendKind,
+    #[serde(default)]
+    secret_storage: SecretStorage,
+    #[serde(default)]
+    encrypted_password: Option<EncryptedSecret>,
+    #[serde(default)]
+    auth_provider: AuthProvider,
+    #[serde(default)]
+    ldap_url: Option<String>,
+    #[serde(default)]
+    ldap_bind_dn: Option<String>,
 }
 
-/// Retrieve API token using user credentials
-async fn get_token(settings: &Settings) -> Result<(Token, User), Error> {
-    let data = serde_json::json!({ "login_id": settings.username,"password": settings.password });
+fn default_true() -> bool {
+    true
+}
 
-    let uri = format!("{}/users/login", settings.api_url);
-    let mut response = surf::post(uri).body_json(&data)?.await?;
-    let token = response.header("Token").ok_or(Error::TokenMissing)?;
-    let token = format!("Bearer {}", token);
+fn default_keyserver_url() -> String {
+    DEFAULT_KEYSERVER_URL.to_string()
+}
 
-    let user_details = response.body_json::<User>().await?;
+/// Build the configured messenger backend
+fn build_backend(settings: Settings) -> Box<dyn MessengerBackend> {
+    match settings.backend {
+        BackendKind::Mattermost => Box::new(Mattermost::new(settings)),
+        BackendKind::Matrix => Box::new(Matrix::new(settings)),
+    }
+}
 
-    Ok((Token(token), user_details))
+/// Strip the ```` ```echo "..." | gpg --decrypt``` ```` wrapper this crate emits,
+/// returning the bare armored ciphertext
+fn extract_ciphertext(post_message: &str) -> Option<String> {
+    let inner = post_message.trim().trim_start_matches("```").trim_end_matches("```");
+    let inner = inner.trim().strip_prefix("echo \"")?;
+    let inner = inner.strip_suffix("\" | gpg --decrypt")?;
+    Some(inner.trim().to_string())
 }
 
-/// Retrieve user by email address
-async fn get_user(settings: &Settings, token: &Token, email: &str) -> Result<User, Error> {
-    let uri = format!("{}/users/email/{}", settings.api_url, email);
-    let user = surf::get(uri)
-        .set_header("Authorization", token.0.clone())
-        .recv_json()
-        .await?;
-    Ok(user)
+/// Colorized label describing the outcome of signature verification
+fn signature_status(verify_result: &gpgme::VerificationResult) -> ColoredString {
+    let signatures: Vec<_> = verify_result.signatures().collect();
+    if signatures.is_empty() {
+        return "unsigned".normal();
+    }
+
+    if signatures.iter().all(|sig| sig.status().is_ok()) {
+        let signer = signatures[0]
+            .fingerprint()
+            .unwrap_or("<unknown signer>");
+        format!("good signature from {}", signer).green()
+    } else if signatures.iter().any(|sig| sig.status().is_ok()) {
+        "partially verified signature".yellow()
+    } else {
+        "bad signature".red()
+    }
 }
 
-/// Create a message channel between sender and recipient
-async fn create_direct_message_channel(
-    settings: &Settings,
-    token: &Token,
-    from: &str,
-    to: &str,
-) -> Result<ChannelId, Error> {
-    let data = serde_json::json!(&[from, to]);
-    let uri = format!("{}/channels/direct", settings.api_url);
-    let response = surf::post(uri)
-        .set_header("Authorization", token.0.clone())
-        .body_json(&data)?
-        .recv_string()
-        .await?;
-    let v: Value = serde_json::from_str(&response)?;
-    let channel_id = ChannelId(v["id"].as_str().unwrap().to_string());
-    Ok(channel_id)
+/// Fetch recent direct-message posts, decrypt any ciphertext they carry and print
+/// the plaintext along with the signature verification status.
+///
+/// `--read` only supports the Mattermost backend for now, since Matrix delivers
+/// messages over a sync stream rather than a simple "list recent posts" endpoint.
+async fn read_messages(settings: &Settings) -> Result<(), Error> {
+    if settings.backend != BackendKind::Mattermost {
+        return Err(Error::Backend(
+            "--read is only supported with the Mattermost backend".to_string(),
+        ));
+    }
+
+    let mut mattermost = Mattermost::new(settings.clone());
+    mattermost.authenticate().await?;
+    let mut ctx = Context::from_protocol(Protocol::OpenPgp)?;
+
+    let channels = mattermost.list_direct_channels().await?;
+    for channel_id in channels {
+        let posts = mattermost.fetch_posts(&channel_id).await?;
+        for post_message in posts {
+            let ciphertext = match extract_ciphertext(&post_message) {
+                Some(ciphertext) => ciphertext,
+                None => continue,
+            };
+
+            if !settings.auto_decrypt {
+                println!("{} Encrypted message (auto-decrypt disabled):\n{}", "•".yellow(), ciphertext);
+                continue;
+            }
+
+            let mut plaintext = Vec::new();
+            if settings.verify_signatures {
+                let verify_result = ctx.decrypt_and_verify(ciphertext.as_bytes(), &mut plaintext)?;
+                println!(
+                    "{} Decrypted message [{}]:\n{}",
+                    "✓".green(),
+                    signature_status(&verify_result),
+                    String::from_utf8_lossy(&plaintext)
+                );
+            } else {
+                ctx.decrypt(ciphertext.as_bytes(), &mut plaintext)?;
+                println!(
+                    "{} Decrypted message:\n{}",
+                    "✓".green(),
+                    String::from_utf8_lossy(&plaintext)
+                );
+            }
+        }
+    }
+
+    Ok(())
 }
 
-/// Send message to channel (recipient)
-async fn create_post(
+/// Describe a key candidate for the interactive selection prompt
+fn describe_key(key: &Key) -> String {
+    let fingerprint = key.fingerprint().unwrap_or("<unknown fingerprint>");
+    let uid = key
+        .user_ids()
+        .next()
+        .and_then(|uid| uid.id().ok())
+        .unwrap_or("<unknown uid>");
+    let expires = key
+        .primary_key()
+        .and_then(|k| k.expires())
+        .map(|t| format!("{:?}", t))
+        .unwrap_or_else(|| "never".to_string());
+    let trust = format!("{:?}", key.owner_trust());
+
+    format!(
+        "{} - {} (expires: {}, trust: {})",
+        fingerprint, uid, expires, trust
+    )
+}
+
+/// Derive the Web Key Directory "advanced" lookup URL for an email address.
+/// Uses the `openpgpkey.` sub-domain and repeats the domain in the path, as
+/// required by the Advanced Method (most real-world deployments, e.g.
+/// mailbox.org, GMX, ProtonMail, only serve this one).
+fn wkd_advanced_url(local_part: &str, domain: &str) -> String {
+    let digest = Sha1::from(local_part.to_lowercase()).digest().bytes();
+    let hash = zbase32::encode_full_bytes(&digest);
+    format!(
+        "https://openpgpkey.{}/.well-known/openpgpkey/{}/hu/{}?l={}",
+        domain, domain, hash, local_part
+    )
+}
+
+/// Derive the Web Key Directory "direct" lookup URL for an email address,
+/// used as a fallback when the domain doesn't run the `openpgpkey.` sub-domain.
+fn wkd_direct_url(local_part: &str, domain: &str) -> String {
+    let digest = Sha1::from(local_part.to_lowercase()).digest().bytes();
+    let hash = zbase32::encode_full_bytes(&digest);
+    format!(
+        "https://{}/.well-known/openpgpkey/hu/{}?l={}",
+        domain, hash, local_part
+    )
+}
+
+/// Try to import a key for `recipient` from the configured keyserver, falling back to WKD
+async fn import_missing_key(
+    ctx: &mut Context,
     settings: &Settings,
-    token: &Token,
-    channel_id: &ChannelId,
-    message: &str,
+    recipient: &str,
 ) -> Result<(), Error> {
-    let data = serde_json::json!({
-        "channel_id": channel_id.0,
-        // "file_ids":[],
-        "message": message,
-    });
-
-    let uri = format!("{}/posts", settings.api_url);
-    surf::post(uri)
-        .set_header("Authorization", token.0.clone())
-        .body_json(&data)?
-        .recv_string()
-        .await?;
-    Ok(())
+    let keyserver_uri = format!(
+        "{}/pks/lookup?op=get&options=mr&search={}",
+        settings.keyserver_url, recipient
+    );
+    if let Ok(armored) = surf::get(keyserver_uri).recv_string().await {
+        if ctx.import(armored.as_bytes()).is_ok() {
+            return Ok(());
+        }
+    }
+
+    if let Some((local_part, domain)) = recipient.split_once('@') {
+        let advanced_uri = wkd_advanced_url(local_part, domain);
+        if let Ok(armored) = surf::get(advanced_uri).recv_bytes().await {
+            ctx.import(&armored)?;
+            return Ok(());
+        }
+
+        let direct_uri = wkd_direct_url(local_part, domain);
+        let armored = surf::get(direct_uri).recv_bytes().await?;
+        ctx.import(&armored)?;
+        return Ok(());
+    }
+
+    Err(Error::KeyNotFound(recipient.to_string()))
+}
+
+/// Resolve a recipient to a single key, prompting the user when more than one key
+/// matches and fetching one from the keyserver/WKD when none is found locally.
+/// The choice is remembered for the remainder of the run.
+async fn resolve_key<'a>(
+    ctx: &mut Context,
+    settings: &Settings,
+    key_choices: &'a mut HashMap<String, Key>,
+    recipient: &str,
+) -> Result<&'a Key, Error> {
+    if !key_choices.contains_key(recipient) {
+        let mut candidates: Vec<Key> = ctx
+            .find_keys(Some(recipient.to_string()))?
+            .filter_map(|k| k.ok())
+            .collect();
+
+        if candidates.is_empty() {
+            import_missing_key(ctx, settings, recipient).await?;
+            candidates = ctx
+                .find_keys(Some(recipient.to_string()))?
+                .filter_map(|k| k.ok())
+                .collect();
+        }
+
+        let key = match candidates.len() {
+            0 => return Err(Error::KeyNotFound(recipient.to_string())),
+            1 => candidates.remove(0),
+            _ => {
+                // When the message argument is absent we've already drained stdin to EOF to read the
+                // message itself, so the Select prompt below has nothing left to read
+                // from a piped stdin. Fail clearly instead of silently hanging/erroring
+                // on an exhausted stream.
+                if !atty::is(atty::Stream::Stdin) {
+                    return Err(Error::Backend(format!(
+                        "multiple keys found for {} and stdin is not a terminal; disambiguate by trimming the matching keys or passing the message as an argument so stdin isn't consumed",
+                        recipient
+                    )));
+                }
+
+                let items: Vec<String> = candidates.iter().map(describe_key).collect();
+                let selection = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt(&format!("Multiple keys found for {}, pick one", recipient))
+                    .items(&items)
+                    .default(0)
+                    .interact()?;
+                candidates.remove(selection)
+            }
+        };
+
+        key_choices.insert(recipient.to_string(), key);
+    }
+
+    Ok(key_choices.get(recipient).unwrap())
 }
 
-/// Retrieve API token, encrypt message per recipient and send it to each recipient
-async fn send_message(settings: &Settings, opt: &Opt, message: &str) -> Result<(), Error> {
-    let (token, user_details) = get_token(settings).await?;
+/// Authenticate against the messenger backend, encrypt message per recipient
+/// and send it to each recipient
+async fn send_message(
+    backend: &mut dyn MessengerBackend,
+    settings: &Settings,
+    opt: &Opt,
+    message: &str,
+) -> Result<(), Error> {
+    backend.authenticate().await?;
 
     let mut ctx = Context::from_protocol(Protocol::OpenPgp)?;
     ctx.set_armor(true);
 
+    let sign = opt.sign || settings.auto_sign;
+    let encrypt_for_self = opt.encrypt_for_self || settings.encrypt_for_self;
+    let mut key_choices: HashMap<String, Key> = HashMap::new();
+    let own_id = backend.own_identifier().to_string();
+
+    let own_key = if encrypt_for_self {
+        Some(
+            resolve_key(&mut ctx, settings, &mut key_choices, &own_id)
+                .await?
+                .clone(),
+        )
+    } else {
+        None
+    };
+
+    let file_contents = opt
+        .file
+        .as_ref()
+        .map(|path| std::fs::read(path))
+        .transpose()?;
+
     for recipient in opt.to.iter() {
-        // Encrypt message per recipient
-        let public_key = ctx.get_key(recipient)?;
+        // Encrypt message per recipient (and for ourselves, if requested)
+        let public_key = resolve_key(&mut ctx, settings, &mut key_choices, recipient)
+            .await?
+            .clone();
+        let mut recipient_keys = vec![&public_key];
+        if let Some(ref own_key) = own_key {
+            recipient_keys.push(own_key);
+        }
+
         let mut ciphertext = Vec::new();
-        if opt.sign {
-            ctx.sign_and_encrypt(Some(&public_key), message, &mut ciphertext)?;
+        if sign {
+            ctx.sign_and_encrypt(recipient_keys.clone(), message, &mut ciphertext)?;
         } else {
-            ctx.encrypt(Some(&public_key), message, &mut ciphertext)?;
+            ctx.encrypt(recipient_keys.clone(), message, &mut ciphertext)?;
         }
 
-        let recipient_user = get_user(settings, &token, &recipient).await?;
-        let channel_id =
-            create_direct_message_channel(settings, &token, &user_details.id, &recipient_user.id)
+        let recipient_id = backend.resolve_recipient(recipient).await?;
+        let channel_id = backend.open_direct_channel(&recipient_id).await?;
+
+        let mut file_ids = Vec::new();
+        if let Some(ref file_contents) = file_contents {
+            let path = opt.file.as_ref().unwrap();
+            let filename = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("attachment")
+                .to_string();
+
+            let mut file_ciphertext = Vec::new();
+            if sign {
+                ctx.sign_and_encrypt(recipient_keys.clone(), &file_contents[..], &mut file_ciphertext)?;
+            } else {
+                ctx.encrypt(recipient_keys.clone(), &file_contents[..], &mut file_ciphertext)?;
+            }
+
+            let armored_filename = format!("{}.gpg.asc", filename);
+            let file_id = backend
+                .upload_file(&channel_id, &armored_filename, &file_ciphertext)
                 .await?;
+            file_ids.push(file_id);
+        }
 
         let cipherstring = std::str::from_utf8(&ciphertext).unwrap();
         let message = format!("```\necho \"\n{}\" | gpg --decrypt\n```", cipherstring);
 
-        create_post(settings, &token, &channel_id, &message).await?;
+        backend.send_post(&channel_id, &message, &file_ids).await?;
 
         print!(
             "{} Successfully sent message\nFROM:\t{}\nTO:\t{}\nFINGERPRINT: {}\nMESSAGE:\n{}\n",
             "✓".green(),
-            user_details.email.magenta(),
-            recipient_user.email.cyan(),
+            own_id.magenta(),
+            recipient.cyan(),
             public_key.fingerprint()?.cyan(),
             message
         );
@@ -195,17 +462,116 @@ fn init_settings() -> Result<Settings, Error> {
         .with_prompt("Login username")
         .interact()?;
 
+    let auth_provider_choices = &[
+        "Username + password",
+        "Personal access token",
+        "LDAP bind (then username + password login)",
+    ];
+    let auth_provider = match Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Authentication method")
+        .items(auth_provider_choices)
+        .default(0)
+        .interact()?
+    {
+        1 => AuthProvider::PersonalAccessToken,
+        2 => AuthProvider::Ldap,
+        _ => AuthProvider::Password,
+    };
+
+    let (ldap_url, ldap_bind_dn) = if auth_provider == AuthProvider::Ldap {
+        let ldap_url: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("LDAP Url (e.g. ldaps://ldap.my-company.com)")
+            .interact()?;
+        let ldap_bind_dn: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("LDAP bind DN")
+            .default(username.clone())
+            .interact()?;
+        (Some(ldap_url), Some(ldap_bind_dn))
+    } else {
+        (None, None)
+    };
+
+    let password_prompt = match auth_provider {
+        AuthProvider::PersonalAccessToken => "Personal access token (will be stored securely)",
+        _ => "Login Password (will be stored securely)",
+    };
     let password: String = PasswordInput::with_theme(&ColorfulTheme::default())
-        .with_prompt("Login Password (will be securely stored in Keyring)")
+        .with_prompt(password_prompt)
         .with_confirmation("Repeat password", "Error: the passwords don't match.")
         .interact()?;
 
-    let keyring = keyring::Keyring::new(KEYRING_SERVICE, &username);
-    keyring.set_password(&password)?;
+    let auto_sign = Confirmation::with_theme(&ColorfulTheme::default())
+        .with_text("Sign every message by default?")
+        .interact()?;
+
+    let encrypt_for_self = Confirmation::with_theme(&ColorfulTheme::default())
+        .with_text("Encrypt every message for yourself by default?")
+        .interact()?;
+
+    let keyserver_url: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Keyserver Url (used to fetch missing recipient keys)")
+        .default(DEFAULT_KEYSERVER_URL.to_string())
+        .interact()?;
+
+    let auto_decrypt = Confirmation::with_theme(&ColorfulTheme::default())
+        .with_text("Automatically decrypt messages in --read mode?")
+        .interact()?;
+
+    let verify_signatures = Confirmation::with_theme(&ColorfulTheme::default())
+        .with_text("Verify signatures when decrypting?")
+        .interact()?;
+
+    let backend_choices = &["Mattermost", "Matrix"];
+    let backend = match Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Messenger backend")
+        .items(backend_choices)
+        .default(0)
+        .interact()?
+    {
+        1 => BackendKind::Matrix,
+        _ => BackendKind::Mattermost,
+    };
+
+    let secret_storage_choices = &["OS Keyring", "Encrypted file (no keyring service available)"];
+    let secret_storage = match Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Where should the login password be stored?")
+        .items(secret_storage_choices)
+        .default(0)
+        .interact()?
+    {
+        1 => SecretStorage::EncryptedFile,
+        _ => SecretStorage::Keyring,
+    };
+
+    let encrypted_password = match secret_storage {
+        SecretStorage::Keyring => {
+            let keyring = keyring::Keyring::new(KEYRING_SERVICE, &username);
+            keyring.set_password(&password)?;
+            None
+        }
+        SecretStorage::EncryptedFile => {
+            let master_passphrase: String = PasswordInput::with_theme(&ColorfulTheme::default())
+                .with_prompt("Master passphrase (encrypts the stored password)")
+                .with_confirmation("Repeat master passphrase", "Error: the passphrases don't match.")
+                .interact()?;
+            Some(secrets::encrypt(&master_passphrase, &username, password.as_bytes())?)
+        }
+    };
 
     let stored_settings = StoredSettings {
         api_url: api_url.clone(),
         username: username.clone(),
+        auto_sign,
+        encrypt_for_self,
+        keyserver_url: keyserver_url.clone(),
+        auto_decrypt,
+        verify_signatures,
+        backend,
+        secret_storage,
+        encrypted_password,
+        auth_provider,
+        ldap_url: ldap_url.clone(),
+        ldap_bind_dn: ldap_bind_dn.clone(),
     };
     let serialized_settings = serde_json::to_vec_pretty(&stored_settings)?;
     let mut settings_path = config_dir().unwrap_or_default();
@@ -217,6 +583,16 @@ fn init_settings() -> Result<Settings, Error> {
         api_url,
         username,
         password,
+        auto_sign,
+        encrypt_for_self,
+        keyserver_url,
+        auto_decrypt,
+        verify_signatures,
+        backend,
+        secret_storage,
+        auth_provider,
+        ldap_url,
+        ldap_bind_dn,
     })
 }
 
@@ -229,13 +605,42 @@ fn load_settings() -> Result<Settings, Error> {
     file.read_to_end(&mut content)?;
     let stored_settings: StoredSettings = serde_json::from_slice(&content)?;
 
-    let keyring = keyring::Keyring::new(KEYRING_SERVICE, &stored_settings.username);
-    let password = keyring.get_password()?;
+    let password = match stored_settings.secret_storage {
+        SecretStorage::Keyring => {
+            let keyring = keyring::Keyring::new(KEYRING_SERVICE, &stored_settings.username);
+            keyring.get_password()?
+        }
+        SecretStorage::EncryptedFile => {
+            let encrypted_password = stored_settings
+                .encrypted_password
+                .as_ref()
+                .ok_or(Error::Crypto)?;
+            let master_passphrase: String = PasswordInput::with_theme(&ColorfulTheme::default())
+                .with_prompt("Master passphrase")
+                .interact()?;
+            let decrypted = secrets::decrypt(
+                &master_passphrase,
+                &stored_settings.username,
+                encrypted_password,
+            )?;
+            String::from_utf8(decrypted).map_err(|_| Error::Crypto)?
+        }
+    };
 
     Ok(Settings {
         api_url: stored_settings.api_url,
         username: stored_settings.username,
         password,
+        auto_sign: stored_settings.auto_sign,
+        encrypt_for_self: stored_settings.encrypt_for_self,
+        keyserver_url: stored_settings.keyserver_url,
+        auto_decrypt: stored_settings.auto_decrypt,
+        verify_signatures: stored_settings.verify_signatures,
+        backend: stored_settings.backend,
+        secret_storage: stored_settings.secret_storage,
+        auth_provider: stored_settings.auth_provider,
+        ldap_url: stored_settings.ldap_url,
+        ldap_bind_dn: stored_settings.ldap_bind_dn,
     })
 }
 
@@ -257,6 +662,10 @@ fn main() -> Result<(), Error> {
         config => config?,
     };
 
+    if opt.read {
+        return task::block_on(read_messages(&settings));
+    }
+
     let message: Result<String, Error> = match opt.message {
         None => {
             // Read message from stdin if it's not passed as parameter
@@ -270,7 +679,9 @@ fn main() -> Result<(), Error> {
         }
         Some(ref message) => Ok(message.to_string()),
     };
-    task::block_on(send_message(&settings, &opt, &message?))?;
+
+    let mut backend = build_backend(settings.clone());
+    task::block_on(send_message(backend.as_mut(), &settings, &opt, &message?))?;
 
     Ok(())
 }
@@ -0,0 +1,83 @@
+use crate::Error;
+use argon2::{self, Config, ThreadMode, Variant, Version};
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// Where the Mattermost password/token is kept
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretStorage {
+    /// OS keyring (via the `keyring` crate); unavailable on headless servers
+    Keyring,
+    /// Argon2i-derived key + ChaCha20-Poly1305, stored in the settings file
+    EncryptedFile,
+}
+
+impl Default for SecretStorage {
+    fn default() -> Self {
+        SecretStorage::Keyring
+    }
+}
+
+/// A secret encrypted with a passphrase-derived key, as stored in the settings file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptedSecret {
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+/// Derive a 32-byte key from a master passphrase using Argon2i, salted with the username.
+///
+/// Argon2 requires a salt of at least 8 bytes, but Mattermost usernames can be
+/// shorter than that, so the salt is the username prefixed with a fixed
+/// app-level segment rather than the raw username bytes.
+fn derive_key(passphrase: &str, username: &str) -> Result<[u8; KEY_LEN], Error> {
+    let config = Config {
+        variant: Variant::Argon2i,
+        version: Version::Version13,
+        hash_length: KEY_LEN as u32,
+        thread_mode: ThreadMode::Sequential,
+        ..Config::default()
+    };
+    let salt = format!("mattercrypt:{}", username);
+    let hash = argon2::hash_raw(passphrase.as_bytes(), salt.as_bytes(), &config)
+        .map_err(|_| Error::Crypto)?;
+    let mut key = [0u8; KEY_LEN];
+    key.copy_from_slice(&hash);
+    Ok(key)
+}
+
+/// Encrypt `plaintext` (e.g. a Mattermost password/token) under a passphrase-derived key
+pub fn encrypt(passphrase: &str, username: &str, plaintext: &[u8]) -> Result<EncryptedSecret, Error> {
+    let key = derive_key(passphrase, username)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext)
+        .map_err(|_| Error::Crypto)?;
+
+    Ok(EncryptedSecret {
+        nonce: nonce_bytes.to_vec(),
+        ciphertext,
+    })
+}
+
+/// Decrypt a secret previously produced by `encrypt`, re-deriving the key from the passphrase
+pub fn decrypt(passphrase: &str, username: &str, secret: &EncryptedSecret) -> Result<Vec<u8>, Error> {
+    let key = derive_key(passphrase, username)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    let nonce = Nonce::from_slice(&secret.nonce);
+
+    cipher
+        .decrypt(nonce, secret.ciphertext.as_ref())
+        .map_err(|_| Error::Crypto)
+}